@@ -0,0 +1,18 @@
+//! Supported Ethereum JSON-RPC API namespaces.
+
+mod trace;
+
+pub use self::trace::Trace;
+
+use crate::Transport;
+
+/// A namespace of RPC calls bound to a particular `Transport`.
+pub trait Namespace<T: Transport>: Clone {
+    /// Creates a new API namespace from the given transport.
+    fn new(transport: T) -> Self
+    where
+        Self: Sized;
+
+    /// Borrows the transport backing this namespace.
+    fn transport(&self) -> &T;
+}
@@ -0,0 +1,218 @@
+//! `Trace` namespace, wrapping the Parity/OpenEthereum ad-hoc trace API.
+
+use crate::{
+    api::Namespace,
+    helpers::{self, CallFuture},
+    types::{BlockNumber, BlockTrace, Bytes, CallRequest, LocalizedTrace, TraceFilter, TraceType, H256},
+    Transport,
+};
+
+/// `Trace` namespace
+#[derive(Debug, Clone)]
+pub struct Trace<T> {
+    transport: T,
+}
+
+impl<T: Transport> Namespace<T> for Trace<T> {
+    fn new(transport: T) -> Self
+    where
+        Self: Sized,
+    {
+        Trace { transport }
+    }
+
+    fn transport(&self) -> &T {
+        &self.transport
+    }
+}
+
+impl<T: Transport> Trace<T> {
+    /// Executes the given call and returns a number of possible traces for it.
+    pub fn call(
+        &self,
+        req: CallRequest,
+        trace_type: Vec<TraceType>,
+        block: Option<BlockNumber>,
+    ) -> CallFuture<BlockTrace, T::Out> {
+        let req = helpers::serialize(&req);
+        let trace_type = helpers::serialize(&trace_type);
+        let block = helpers::serialize(&block.unwrap_or_default());
+        CallFuture::new(self.transport.execute("trace_call", vec![req, trace_type, block]))
+    }
+
+    /// Performs multiple call traces on top of the same block, i.e. transaction `n` will be
+    /// executed on top of a pending block with all `n - 1` transactions applied (traced) first.
+    pub fn call_many(
+        &self,
+        reqs: Vec<(CallRequest, Vec<TraceType>)>,
+        block: Option<BlockNumber>,
+    ) -> CallFuture<Vec<BlockTrace>, T::Out> {
+        let reqs = helpers::serialize(&reqs);
+        let block = helpers::serialize(&block.unwrap_or_default());
+        CallFuture::new(self.transport.execute("trace_callMany", vec![reqs, block]))
+    }
+
+    /// Traces a call to `eth_sendRawTransaction` without making the call, returning the traces.
+    pub fn raw_transaction(&self, data: Bytes, trace_type: Vec<TraceType>) -> CallFuture<BlockTrace, T::Out> {
+        let data = helpers::serialize(&data);
+        let trace_type = helpers::serialize(&trace_type);
+        CallFuture::new(self.transport.execute("trace_rawTransaction", vec![data, trace_type]))
+    }
+
+    /// Replays a transaction, returning the traces.
+    pub fn replay_transaction(&self, hash: H256, trace_type: Vec<TraceType>) -> CallFuture<BlockTrace, T::Out> {
+        let hash = helpers::serialize(&hash);
+        let trace_type = helpers::serialize(&trace_type);
+        CallFuture::new(self.transport.execute("trace_replayTransaction", vec![hash, trace_type]))
+    }
+
+    /// Replays all transactions in a block, returning the requested traces for each transaction.
+    pub fn replay_block_transactions(
+        &self,
+        block: BlockNumber,
+        trace_type: Vec<TraceType>,
+    ) -> CallFuture<Vec<BlockTrace>, T::Out> {
+        let block = helpers::serialize(&block);
+        let trace_type = helpers::serialize(&trace_type);
+        CallFuture::new(self.transport.execute("trace_replayBlockTransactions", vec![block, trace_type]))
+    }
+
+    /// Returns traces matching the given filter, without requiring the caller to pull whole blocks.
+    pub fn filter(&self, filter: TraceFilter) -> CallFuture<Vec<LocalizedTrace>, T::Out> {
+        let filter = helpers::serialize(&filter);
+        CallFuture::new(self.transport.execute("trace_filter", vec![filter]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TraceFilterBuilder;
+    use futures::future;
+    use serde_json::Value;
+    use std::cell::RefCell;
+
+    /// Records the method name and params passed to `Transport::execute`, for asserting on the
+    /// exact JSON-RPC call a namespace method builds.
+    #[derive(Debug, Default, Clone)]
+    struct TestTransport {
+        requests: RefCell<Vec<(String, Vec<Value>)>>,
+    }
+
+    impl Transport for TestTransport {
+        type Out = future::Ready<Result<Value, ()>>;
+
+        fn execute(&self, method: &str, params: Vec<Value>) -> Self::Out {
+            self.requests.borrow_mut().push((method.to_string(), params));
+            future::ok(Value::Null)
+        }
+    }
+
+    impl TestTransport {
+        fn assert_request(&self, index: usize, method: &str, params: &[Value]) {
+            let requests = self.requests.borrow();
+            let (actual_method, actual_params) = requests.get(index).expect("no request recorded at index");
+            assert_eq!(actual_method, method);
+            assert_eq!(actual_params.as_slice(), params);
+        }
+    }
+
+    #[test]
+    fn test_call_params() {
+        let transport = TestTransport::default();
+        let trace = Trace::new(transport.clone());
+        let req = CallRequest::default();
+        let trace_type = vec![TraceType::Trace];
+
+        let _ = trace.call(req.clone(), trace_type.clone(), None);
+
+        transport.assert_request(
+            0,
+            "trace_call",
+            &[
+                helpers::serialize(&req),
+                helpers::serialize(&trace_type),
+                helpers::serialize(&BlockNumber::Latest),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_call_many_params() {
+        let transport = TestTransport::default();
+        let trace = Trace::new(transport.clone());
+        let reqs = vec![(CallRequest::default(), vec![TraceType::Trace])];
+
+        let _ = trace.call_many(reqs.clone(), Some(BlockNumber::Pending));
+
+        transport.assert_request(
+            0,
+            "trace_callMany",
+            &[helpers::serialize(&reqs), helpers::serialize(&BlockNumber::Pending)],
+        );
+    }
+
+    #[test]
+    fn test_raw_transaction_params() {
+        let transport = TestTransport::default();
+        let trace = Trace::new(transport.clone());
+        let data = Bytes(vec![1, 2, 3]);
+        let trace_type = vec![TraceType::VmTrace];
+
+        let _ = trace.raw_transaction(data.clone(), trace_type.clone());
+
+        transport.assert_request(
+            0,
+            "trace_rawTransaction",
+            &[helpers::serialize(&data), helpers::serialize(&trace_type)],
+        );
+    }
+
+    #[test]
+    fn test_replay_transaction_params() {
+        let transport = TestTransport::default();
+        let trace = Trace::new(transport.clone());
+        let hash = H256::from_low_u64_be(1);
+        let trace_type = vec![TraceType::StateDiff];
+
+        let _ = trace.replay_transaction(hash, trace_type.clone());
+
+        transport.assert_request(
+            0,
+            "trace_replayTransaction",
+            &[helpers::serialize(&hash), helpers::serialize(&trace_type)],
+        );
+    }
+
+    #[test]
+    fn test_replay_block_transactions_params() {
+        let transport = TestTransport::default();
+        let trace = Trace::new(transport.clone());
+        let trace_type = vec![TraceType::Trace, TraceType::VmTrace];
+
+        let _ = trace.replay_block_transactions(BlockNumber::Number(5.into()), trace_type.clone());
+
+        transport.assert_request(
+            0,
+            "trace_replayBlockTransactions",
+            &[
+                helpers::serialize(&BlockNumber::Number(5.into())),
+                helpers::serialize(&trace_type),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_filter_params() {
+        let transport = TestTransport::default();
+        let trace = Trace::new(transport.clone());
+        let filter = TraceFilterBuilder::default()
+            .from_block(BlockNumber::Number(1.into()))
+            .to_block(BlockNumber::Number(2.into()))
+            .build();
+
+        let _ = trace.filter(filter.clone());
+
+        transport.assert_request(0, "trace_filter", &[helpers::serialize(&filter)]);
+    }
+}
@@ -1,7 +1,8 @@
 //! Types for the Parity Ad-Hoc Trace API
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 
-use crate::types::{Action, ActionType, Bytes, Res, H160, H256, U256};
+use crate::types::{Action, ActionType, BlockNumber, Bytes, Res, H160, H256, U256};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize)]
@@ -70,6 +71,166 @@ pub struct AccountDiff {
 /// Serde-friendly `StateDiff` shadow.
 pub struct StateDiff(pub BTreeMap<H160, AccountDiff>);
 
+/// A minimal account state: the pre/post-state input and output of `StateDiff::apply`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Account {
+    /// The account's balance.
+    pub balance: U256,
+    /// The account's nonce.
+    pub nonce: U256,
+    /// The account's code.
+    pub code: Bytes,
+    /// The account's storage.
+    pub storage: BTreeMap<H256, H256>,
+}
+
+/// An error produced while applying a `StateDiff` onto a pre-state map.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateDiffError {
+    /// An account diff referenced an account that is not present in the supplied pre-state and
+    /// does not create it (none of its balance, nonce or code is `Born`).
+    AccountNotFound(H160),
+    /// A `Changed`/`Died` diff's expected prior value did not match the account's current value.
+    PreconditionMismatch {
+        /// The account the mismatch occurred on.
+        address: H160,
+        /// Which field (or storage key) the mismatch occurred on.
+        field: String,
+    },
+}
+
+impl fmt::Display for StateDiffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateDiffError::AccountNotFound(address) => {
+                write!(f, "account {:?} not found in the supplied pre-state", address)
+            }
+            StateDiffError::PreconditionMismatch { address, field } => {
+                write!(f, "precondition mismatch on {} of account {:?}", field, address)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StateDiffError {}
+
+fn apply_diff_field<T: Clone + PartialEq + Default>(
+    diff: &Diff<T>,
+    current: &mut T,
+    address: H160,
+    field: impl Into<String>,
+) -> Result<(), StateDiffError> {
+    match diff {
+        Diff::Same => {}
+        Diff::Born(value) => *current = value.clone(),
+        Diff::Died(value) => {
+            if *current != *value {
+                return Err(StateDiffError::PreconditionMismatch {
+                    address,
+                    field: field.into(),
+                });
+            }
+            *current = T::default();
+        }
+        Diff::Changed(ChangedType { from, to }) => {
+            if *current != *from {
+                return Err(StateDiffError::PreconditionMismatch {
+                    address,
+                    field: field.into(),
+                });
+            }
+            *current = to.clone();
+        }
+    }
+    Ok(())
+}
+
+impl<T: Clone> Diff<T> {
+    /// Returns the inverse of this diff: `Born` becomes `Died` and vice versa, and `Changed`'s
+    /// `from`/`to` are swapped. `Same` is its own inverse.
+    pub fn invert(&self) -> Diff<T> {
+        match self {
+            Diff::Same => Diff::Same,
+            Diff::Born(value) => Diff::Died(value.clone()),
+            Diff::Died(value) => Diff::Born(value.clone()),
+            Diff::Changed(ChangedType { from, to }) => Diff::Changed(ChangedType {
+                from: to.clone(),
+                to: from.clone(),
+            }),
+        }
+    }
+}
+
+impl AccountDiff {
+    fn apply(&self, address: H160, accounts: &mut BTreeMap<H160, Account>) -> Result<(), StateDiffError> {
+        let account_born = matches!(self.balance, Diff::Born(_))
+            || matches!(self.nonce, Diff::Born(_))
+            || matches!(self.code, Diff::Born(_));
+        let account_died = matches!(self.balance, Diff::Died(_))
+            && matches!(self.nonce, Diff::Died(_))
+            && matches!(self.code, Diff::Died(_));
+
+        if !accounts.contains_key(&address) {
+            if account_born {
+                accounts.insert(address, Account::default());
+            } else {
+                return Err(StateDiffError::AccountNotFound(address));
+            }
+        }
+        let account = accounts.get_mut(&address).expect("just checked or inserted above");
+
+        apply_diff_field(&self.balance, &mut account.balance, address, "balance")?;
+        apply_diff_field(&self.nonce, &mut account.nonce, address, "nonce")?;
+        apply_diff_field(&self.code, &mut account.code, address, "code")?;
+        for (key, diff) in &self.storage {
+            let current = account.storage.entry(*key).or_insert_with(H256::default);
+            apply_diff_field(diff, current, address, format!("storage[{:?}]", key))?;
+        }
+
+        if account_died {
+            accounts.remove(&address);
+        }
+
+        Ok(())
+    }
+
+    fn invert(&self) -> AccountDiff {
+        AccountDiff {
+            balance: self.balance.invert(),
+            nonce: self.nonce.invert(),
+            code: self.code.invert(),
+            storage: self.storage.iter().map(|(key, diff)| (*key, diff.invert())).collect(),
+        }
+    }
+}
+
+impl StateDiff {
+    /// Applies this diff forward onto `accounts`, mutating it in place.
+    ///
+    /// `Born` sets the value, inserting the account if it is absent; `Died` asserts the prior
+    /// value matches and resets it to its default, and an account whose balance, nonce and code
+    /// all die is removed (a dead storage slot stays in the map, holding zero); `Changed`
+    /// asserts the account's current value matches `from` before setting it to `to`, and `Same`
+    /// leaves the value untouched.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a diff references an account absent from `accounts` that it does not
+    /// create, or if a `Changed`/`Died` precondition does not match the supplied pre-state.
+    pub fn apply(&self, accounts: &mut BTreeMap<H160, Account>) -> Result<(), StateDiffError> {
+        for (address, account_diff) in &self.0 {
+            account_diff.apply(*address, accounts)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the inverse of this diff, swapping `Born`/`Died` and flipping `Changed`'s
+    /// `from`/`to`.
+    pub fn invert(&self) -> StateDiff {
+        StateDiff(self.0.iter().map(|(address, diff)| (*address, diff.invert())).collect())
+    }
+}
+
 // ------------------ Trace -------------
 /// Trace
 #[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
@@ -90,6 +251,306 @@ pub struct TransactionTrace {
     pub error: Option<String>,
 }
 
+/// Typed representation of the EVM failure reasons reported in `TransactionTrace::error`.
+///
+/// Parses the free-form string the node returns; anything not recognized is preserved via
+/// `Other` rather than being lost. Several of the node's messages interpolate dynamic data
+/// (the offending opcode, wanted/available stack depth, ...), so those variants are recognized
+/// by prefix rather than by an exact match.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceError {
+    /// The call ran out of gas.
+    OutOfGas,
+    /// Ill-formed jump destination.
+    BadJumpDestination,
+    /// Ill-formed instruction.
+    BadInstruction,
+    /// Not enough items were on the stack for the current instruction.
+    StackUnderflow,
+    /// Too many items were pushed onto the stack.
+    OutOfStack,
+    /// A built-in (precompiled) contract ran out of gas.
+    BuiltInOutOfGas,
+    /// A built-in (precompiled) contract was given an invalid proof.
+    BuiltInBadProof,
+    /// The call was reverted via the `REVERT` instruction.
+    Reverted,
+    /// Any other error string, preserved verbatim.
+    Other(String),
+}
+
+impl TraceError {
+    fn as_node_str(&self) -> &str {
+        match self {
+            TraceError::OutOfGas => "Out of gas",
+            TraceError::BadJumpDestination => "Bad jump destination",
+            TraceError::BadInstruction => "Bad instruction",
+            TraceError::StackUnderflow => "Stack underflow",
+            TraceError::OutOfStack => "Out of stack",
+            TraceError::BuiltInOutOfGas => "Built-in failed: out of gas",
+            TraceError::BuiltInBadProof => "Built-in failed: invalid proof",
+            TraceError::Reverted => "Reverted",
+            TraceError::Other(s) => s,
+        }
+    }
+
+    /// Parses a node-reported error string into a `TraceError`.
+    ///
+    /// `OutOfGas` and `Reverted` are static, argument-less messages and are matched exactly;
+    /// every other recognized variant interpolates dynamic data into its message (e.g. the
+    /// opcode or stack depth), so those are matched by prefix instead.
+    fn from_node_str(s: &str) -> Self {
+        match s {
+            "Out of gas" => TraceError::OutOfGas,
+            "Reverted" => TraceError::Reverted,
+            s if s.starts_with("Bad jump destination") => TraceError::BadJumpDestination,
+            s if s.starts_with("Bad instruction") => TraceError::BadInstruction,
+            s if s.starts_with("Stack underflow") => TraceError::StackUnderflow,
+            s if s.starts_with("Out of stack") => TraceError::OutOfStack,
+            s if s.starts_with("Built-in failed") && s.contains("gas") => TraceError::BuiltInOutOfGas,
+            s if s.starts_with("Built-in failed") && s.contains("proof") => TraceError::BuiltInBadProof,
+            other => TraceError::Other(other.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TraceError {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(TraceError::from_node_str(&s))
+    }
+}
+
+impl Serialize for TraceError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_node_str())
+    }
+}
+
+impl TransactionTrace {
+    /// Parses the raw `error` string into a typed `TraceError`, if present.
+    ///
+    /// The untyped `error` field is kept as-is for backward compatibility; this is purely an
+    /// additional, more convenient accessor.
+    pub fn typed_error(&self) -> Option<TraceError> {
+        self.error.as_deref().map(TraceError::from_node_str)
+    }
+}
+
+/// A node in the call tree reassembled from the flat `trace_address` paths of a
+/// `Vec<TransactionTrace>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallTreeNode {
+    /// The trace at this node.
+    pub trace: TransactionTrace,
+    /// The nested CALL/CREATE/DELEGATECALL frames invoked by this trace.
+    pub children: Vec<CallTreeNode>,
+}
+
+/// An error produced while reassembling a flat trace list into a `CallTreeNode` tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallTreeError {
+    /// No trace has an empty `trace_address`, so there is no root to build the tree from.
+    MissingRoot,
+    /// More than one trace has an empty `trace_address`.
+    MultipleRoots,
+    /// A trace's parent path (its `trace_address` with the last element dropped) does not match
+    /// any other trace in the set.
+    MissingParent(Vec<usize>),
+    /// A node's declared `subtraces` count does not match the number of children found for it.
+    SubtracesMismatch {
+        /// The `trace_address` of the offending node.
+        trace_address: Vec<usize>,
+        /// The `subtraces` count the node declared.
+        declared: usize,
+        /// The number of children actually found for the node.
+        actual: usize,
+    },
+}
+
+impl fmt::Display for CallTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallTreeError::MissingRoot => write!(f, "no trace with an empty trace_address was found"),
+            CallTreeError::MultipleRoots => write!(f, "more than one trace with an empty trace_address was found"),
+            CallTreeError::MissingParent(path) => write!(f, "no parent trace found for trace_address {:?}", path),
+            CallTreeError::SubtracesMismatch {
+                trace_address,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "trace_address {:?} declared {} subtraces but {} were found",
+                trace_address, declared, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CallTreeError {}
+
+/// Reassembles a flat `Vec<TransactionTrace>` (as returned by `trace_replayTransaction`) into a
+/// recursive call tree, using each trace's `trace_address` path to locate its parent.
+pub fn build_call_tree(mut traces: Vec<TransactionTrace>) -> Result<CallTreeNode, CallTreeError> {
+    traces.sort_by(|a, b| {
+        a.trace_address
+            .len()
+            .cmp(&b.trace_address.len())
+            .then_with(|| a.trace_address.cmp(&b.trace_address))
+    });
+
+    let root_index = traces
+        .iter()
+        .position(|trace| trace.trace_address.is_empty())
+        .ok_or(CallTreeError::MissingRoot)?;
+    if traces.iter().filter(|trace| trace.trace_address.is_empty()).count() > 1 {
+        return Err(CallTreeError::MultipleRoots);
+    }
+
+    let known_paths: BTreeSet<Vec<usize>> = traces.iter().map(|trace| trace.trace_address.clone()).collect();
+    let mut children_by_parent: BTreeMap<Vec<usize>, Vec<usize>> = BTreeMap::new();
+    for (index, trace) in traces.iter().enumerate() {
+        if trace.trace_address.is_empty() {
+            continue;
+        }
+        let parent_path = trace.trace_address[..trace.trace_address.len() - 1].to_vec();
+        if !known_paths.contains(&parent_path) {
+            return Err(CallTreeError::MissingParent(trace.trace_address.clone()));
+        }
+        children_by_parent.entry(parent_path).or_default().push(index);
+    }
+
+    fn build(
+        index: usize,
+        traces: &[TransactionTrace],
+        children_by_parent: &BTreeMap<Vec<usize>, Vec<usize>>,
+    ) -> Result<CallTreeNode, CallTreeError> {
+        let trace = traces[index].clone();
+        let child_indices = children_by_parent.get(&trace.trace_address).cloned().unwrap_or_default();
+
+        let mut children = Vec::with_capacity(child_indices.len());
+        for child_index in child_indices {
+            children.push(build(child_index, traces, children_by_parent)?);
+        }
+
+        if children.len() != trace.subtraces {
+            return Err(CallTreeError::SubtracesMismatch {
+                trace_address: trace.trace_address.clone(),
+                declared: trace.subtraces,
+                actual: children.len(),
+            });
+        }
+
+        Ok(CallTreeNode { trace, children })
+    }
+
+    build(root_index, &traces, &children_by_parent)
+}
+
+/// A `TransactionTrace` located within a block, as returned by `trace_filter`.
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct LocalizedTrace {
+    /// Trace address
+    #[serde(rename = "traceAddress")]
+    pub trace_address: Vec<usize>,
+    /// Subtraces
+    pub subtraces: usize,
+    /// Action
+    pub action: Action,
+    /// Action Type
+    #[serde(rename = "type")]
+    pub action_type: ActionType,
+    /// Result
+    pub result: Option<Res>,
+    /// Error
+    pub error: Option<String>,
+    /// Number of the block in which this trace occurred
+    #[serde(rename = "blockNumber")]
+    pub block_number: u64,
+    /// Hash of the block in which this trace occurred
+    #[serde(rename = "blockHash")]
+    pub block_hash: H256,
+    /// Hash of the transaction that produced this trace, if any
+    #[serde(rename = "transactionHash")]
+    pub transaction_hash: Option<H256>,
+    /// Position of the transaction within the block, if any
+    #[serde(rename = "transactionPosition")]
+    pub transaction_position: Option<usize>,
+}
+
+/// A query for historical internal transactions via `trace_filter`.
+#[derive(Debug, Default, PartialEq, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from_block: Option<BlockNumber>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to_block: Option<BlockNumber>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    from_address: Vec<H160>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    to_address: Vec<H160>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    count: Option<usize>,
+}
+
+/// Builder for a `TraceFilter`.
+#[derive(Debug, Default, Clone)]
+pub struct TraceFilterBuilder {
+    filter: TraceFilter,
+}
+
+impl TraceFilterBuilder {
+    /// Traces starting at this block.
+    pub fn from_block(mut self, block: BlockNumber) -> Self {
+        self.filter.from_block = Some(block);
+        self
+    }
+
+    /// Traces ending at this block.
+    pub fn to_block(mut self, block: BlockNumber) -> Self {
+        self.filter.to_block = Some(block);
+        self
+    }
+
+    /// Only traces originating from one of these addresses.
+    pub fn from_address(mut self, addresses: Vec<H160>) -> Self {
+        self.filter.from_address = addresses;
+        self
+    }
+
+    /// Only traces sent to one of these addresses.
+    pub fn to_address(mut self, addresses: Vec<H160>) -> Self {
+        self.filter.to_address = addresses;
+        self
+    }
+
+    /// Number of leading traces to skip in the result set.
+    pub fn after(mut self, after: usize) -> Self {
+        self.filter.after = Some(after);
+        self
+    }
+
+    /// Maximum number of traces to return.
+    pub fn count(mut self, count: usize) -> Self {
+        self.filter.count = Some(count);
+        self
+    }
+
+    /// Builds the `TraceFilter`.
+    pub fn build(self) -> TraceFilter {
+        self.filter
+    }
+}
+
 // ---------------- VmTrace ------------------------------
 #[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
 /// A record of a full VM trace for a CALL/CREATE.
@@ -148,6 +609,72 @@ pub struct StorageDiff {
     pub val: U256,
 }
 
+/// A reconstructed snapshot of VM state after a single operation.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VMOperationSnapshot {
+    /// The program counter.
+    pub pc: usize,
+    /// The gas cost for this instruction.
+    pub cost: u64,
+    /// The total gas used after this instruction, if it executed.
+    pub used: Option<u64>,
+    /// The full memory contents after this instruction.
+    pub memory: Vec<u8>,
+    /// The full storage contents after this instruction.
+    pub storage: BTreeMap<U256, U256>,
+    /// The history of items pushed onto the stack up to and including this instruction; this is
+    /// *not* the current stack depth, since pops are not tracked here. Callers can pop manually,
+    /// as each instruction's effect on the stack is already known from `pc`.
+    pub stack: Vec<U256>,
+    /// Reconstructed snapshot of the nested CALL/CREATE sub-trace, if any.
+    pub sub: Option<Vec<VMOperationSnapshot>>,
+}
+
+impl VMTrace {
+    /// Reconstructs the full memory and storage state, and the history of pushed stack items,
+    /// after each operation from the recorded deltas, recursing into nested CALL/CREATE
+    /// sub-traces.
+    ///
+    /// Operations that did not execute (`ex.is_none()`, e.g. reverted or halting instructions)
+    /// carry forward the prior snapshot unchanged.
+    pub fn reconstruct(&self) -> Vec<VMOperationSnapshot> {
+        let mut memory: Vec<u8> = Vec::new();
+        let mut storage: BTreeMap<U256, U256> = BTreeMap::new();
+        let mut stack: Vec<U256> = Vec::new();
+        let mut out = Vec::with_capacity(self.ops.len());
+
+        for op in &self.ops {
+            if let Some(ex) = &op.ex {
+                if let Some(diff) = &ex.mem {
+                    let end = diff.off + diff.data.0.len();
+                    if memory.len() < end {
+                        memory.resize(end, 0);
+                    }
+                    memory[diff.off..end].copy_from_slice(&diff.data.0);
+                }
+                if let Some(diff) = &ex.store {
+                    storage.insert(diff.key, diff.val);
+                }
+                stack.extend(ex.push.iter().copied());
+            }
+
+            let sub = op.sub.as_ref().map(VMTrace::reconstruct);
+
+            out.push(VMOperationSnapshot {
+                pc: op.pc,
+                cost: op.cost,
+                used: op.ex.as_ref().map(|ex| ex.used),
+                memory: memory.clone(),
+                storage: storage.clone(),
+                stack: stack.clone(),
+                sub,
+            });
+        }
+
+        out
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,4 +708,243 @@ mod tests {
     fn test_deserialize_blocktraces() {
         let _traces: Vec<BlockTrace> = serde_json::from_str(EXAMPLE_TRACES).unwrap();
     }
+
+    #[test]
+    fn test_vmtrace_reconstruct() {
+        let vm_trace = VMTrace {
+            code: Bytes(vec![]),
+            ops: vec![
+                VMOperation {
+                    pc: 0,
+                    cost: 3,
+                    ex: Some(VMExecutedOperation {
+                        used: 100_000,
+                        push: vec![U256::from(1)],
+                        mem: Some(MemoryDiff {
+                            off: 0,
+                            data: Bytes(vec![0xff, 0xff]),
+                        }),
+                        store: Some(StorageDiff {
+                            key: U256::from(1),
+                            val: U256::from(42),
+                        }),
+                    }),
+                    sub: None,
+                },
+                VMOperation {
+                    pc: 1,
+                    cost: 5,
+                    ex: Some(VMExecutedOperation {
+                        used: 99_997,
+                        push: vec![U256::from(2), U256::from(3)],
+                        mem: None,
+                        store: None,
+                    }),
+                    sub: None,
+                },
+                VMOperation {
+                    pc: 2,
+                    cost: 2,
+                    ex: None,
+                    sub: None,
+                },
+            ],
+        };
+
+        let snapshots = vm_trace.reconstruct();
+
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(snapshots[0].memory, vec![0xff, 0xff]);
+        assert_eq!(snapshots[0].storage.get(&U256::from(1)), Some(&U256::from(42)));
+        assert_eq!(snapshots[0].stack, vec![U256::from(1)]);
+
+        // A second push accumulates onto the existing stack rather than replacing it.
+        assert_eq!(snapshots[1].stack, vec![U256::from(1), U256::from(2), U256::from(3)]);
+
+        // The halting op at index 2 has no `ex`, so it carries forward the prior snapshot.
+        assert_eq!(snapshots[2].memory, snapshots[1].memory);
+        assert_eq!(snapshots[2].storage, snapshots[1].storage);
+        assert_eq!(snapshots[2].stack, snapshots[1].stack);
+        assert_eq!(snapshots[2].used, None);
+    }
+
+    #[test]
+    fn test_trace_error_roundtrip() {
+        let err: TraceError = serde_json::from_str(r#""Out of gas""#).unwrap();
+        assert_eq!(err, TraceError::OutOfGas);
+        assert_eq!(serde_json::to_string(&err).unwrap(), r#""Out of gas""#);
+
+        let unknown: TraceError = serde_json::from_str(r#""some future node-specific failure""#).unwrap();
+        assert_eq!(unknown, TraceError::Other("some future node-specific failure".into()));
+    }
+
+    #[test]
+    fn test_trace_error_parses_dynamic_messages() {
+        // These mirror the node's `vm::Error` Display impl, which interpolates the opcode /
+        // wanted-vs-available depth into the message rather than emitting a fixed string.
+        let cases = [
+            (r#""Bad jump destination 3a""#, TraceError::BadJumpDestination),
+            (r#""Bad instruction fe""#, TraceError::BadInstruction),
+            (r#""Stack underflow DUP1 1/0""#, TraceError::StackUnderflow),
+            (r#""Out of stack PUSH1 1025/1024""#, TraceError::OutOfStack),
+            (r#""Built-in failed: out of gas""#, TraceError::BuiltInOutOfGas),
+            (r#""Built-in failed: invalid proof""#, TraceError::BuiltInBadProof),
+            (r#""Reverted""#, TraceError::Reverted),
+        ];
+
+        for (json, expected) in cases {
+            let parsed: TraceError = serde_json::from_str(json).unwrap();
+            assert_eq!(parsed, expected, "parsing {}", json);
+        }
+    }
+
+    /// Builds a minimal `call` `TransactionTrace` at the given address/subtrace count; the
+    /// action/result contents themselves are irrelevant to the tree-building algorithm.
+    fn trace_at(trace_address: Vec<usize>, subtraces: usize) -> TransactionTrace {
+        let json = serde_json::json!({
+            "action": {
+                "callType": "call",
+                "from": "0x0000000000000000000000000000000000000000",
+                "to": "0x0000000000000000000000000000000000000000",
+                "gas": "0x0",
+                "input": "0x",
+                "value": "0x0",
+            },
+            "result": {
+                "gasUsed": "0x0",
+                "output": "0x",
+            },
+            "subtraces": subtraces,
+            "traceAddress": trace_address,
+            "type": "call",
+            "error": null,
+        });
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_build_call_tree_nested() {
+        let traces = vec![
+            trace_at(vec![0], 0),
+            trace_at(vec![], 2),
+            trace_at(vec![1], 1),
+            trace_at(vec![1, 0], 0),
+        ];
+
+        let root = build_call_tree(traces).unwrap();
+
+        assert_eq!(root.trace.trace_address, Vec::<usize>::new());
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[0].trace.trace_address, vec![0]);
+        assert_eq!(root.children[0].children.len(), 0);
+        assert_eq!(root.children[1].trace.trace_address, vec![1]);
+        assert_eq!(root.children[1].children.len(), 1);
+        assert_eq!(root.children[1].children[0].trace.trace_address, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_build_call_tree_missing_root() {
+        let traces = vec![trace_at(vec![0], 0)];
+
+        let err = build_call_tree(traces).unwrap_err();
+        assert_eq!(err, CallTreeError::MissingRoot);
+    }
+
+    #[test]
+    fn test_build_call_tree_multiple_roots() {
+        let traces = vec![trace_at(vec![], 0), trace_at(vec![], 0)];
+
+        let err = build_call_tree(traces).unwrap_err();
+        assert_eq!(err, CallTreeError::MultipleRoots);
+    }
+
+    #[test]
+    fn test_build_call_tree_missing_parent() {
+        let traces = vec![trace_at(vec![], 0), trace_at(vec![0, 0], 0)];
+
+        let err = build_call_tree(traces).unwrap_err();
+        assert_eq!(err, CallTreeError::MissingParent(vec![0, 0]));
+    }
+
+    #[test]
+    fn test_build_call_tree_subtraces_mismatch() {
+        let traces = vec![trace_at(vec![], 2), trace_at(vec![0], 0)];
+
+        let err = build_call_tree(traces).unwrap_err();
+        assert_eq!(
+            err,
+            CallTreeError::SubtracesMismatch {
+                trace_address: vec![],
+                declared: 2,
+                actual: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_state_diff_apply_and_invert() {
+        let address = H160::from_low_u64_be(1);
+        let mut accounts = BTreeMap::new();
+        accounts.insert(
+            address,
+            Account {
+                balance: U256::from(10),
+                nonce: U256::from(1),
+                code: Bytes(vec![]),
+                storage: BTreeMap::new(),
+            },
+        );
+
+        let mut account_diff = AccountDiff {
+            balance: Diff::Changed(ChangedType {
+                from: U256::from(10),
+                to: U256::from(20),
+            }),
+            nonce: Diff::Same,
+            code: Diff::Same,
+            storage: BTreeMap::new(),
+        };
+        account_diff
+            .storage
+            .insert(H256::zero(), Diff::Born(H256::from_low_u64_be(42)));
+        let diff = StateDiff(BTreeMap::from_iter([(address, account_diff)]));
+
+        diff.apply(&mut accounts).unwrap();
+        let account = accounts.get(&address).unwrap();
+        assert_eq!(account.balance, U256::from(20));
+        assert_eq!(account.storage.get(&H256::zero()), Some(&H256::from_low_u64_be(42)));
+
+        let inverted = diff.invert();
+        inverted.apply(&mut accounts).unwrap();
+        let account = accounts.get(&address).unwrap();
+        assert_eq!(account.balance, U256::from(10));
+        assert_eq!(account.storage.get(&H256::zero()), Some(&H256::zero()));
+    }
+
+    #[test]
+    fn test_state_diff_apply_precondition_mismatch() {
+        let address = H160::from_low_u64_be(2);
+        let mut accounts = BTreeMap::new();
+        accounts.insert(address, Account::default());
+
+        let account_diff = AccountDiff {
+            balance: Diff::Changed(ChangedType {
+                from: U256::from(5),
+                to: U256::from(6),
+            }),
+            nonce: Diff::Same,
+            code: Diff::Same,
+            storage: BTreeMap::new(),
+        };
+        let diff = StateDiff(BTreeMap::from_iter([(address, account_diff)]));
+
+        let err = diff.apply(&mut accounts).unwrap_err();
+        assert_eq!(
+            err,
+            StateDiffError::PreconditionMismatch {
+                address,
+                field: "balance".to_string(),
+            }
+        );
+    }
 }